@@ -1,23 +1,35 @@
 //! Extract the contents to a directory on disk.
 
-use std::{path::PathBuf, sync::LazyLock};
+use std::{
+    collections::HashSet,
+    future::Future,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    str::FromStr,
+    sync::LazyLock,
+};
 
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
 use async_tempfile::TempDir;
-use boxpop::{prelude::*, Authentication};
+use boxpop::{prelude::*, Authentication, Sha256Reader};
 use clap::Parser;
-use color_eyre::{eyre::Context, Result};
+use color_eyre::{
+    eyre::{bail, ensure, Context},
+    Result,
+};
 use console::{style, Emoji};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use oci_client::{
     client::{ClientConfig, SizedStream},
     manifest::OciDescriptor,
-    secrets::RegistryAuth,
     Client, Reference,
 };
 use tokio::{
-    io::{AsyncWriteExt, BufReader},
+    io::{AsyncRead, AsyncWriteExt, BufReader},
     task::JoinSet,
 };
+use tokio_stream::StreamExt;
+use tokio_tar::Archive;
 use tokio_util::io::StreamReader;
 
 /// Options for the `extract` subcommand.
@@ -34,24 +46,39 @@ pub struct Options {
     #[clap(from_global)]
     password: Option<String>,
 
+    /// Select a platform for multi-platform images, as `os/arch[/variant]`.
+    /// If not set, the platform this program is running on is used.
+    #[clap(from_global)]
+    platform: Option<Platform>,
+
     /// The directory to which the content should be written.
     /// If not set, a temporary directory is created; its path is emitted to stdout.
     #[clap(short, long)]
     output: Option<OutputDir>,
+
+    /// Only extract entries whose squashed path matches this glob (repeatable).
+    /// Globs are matched against the path within the image, e.g. `usr/bin/*` or `**/*.so`.
+    /// When omitted, the entire flattened rootfs is extracted.
+    #[clap(long = "path")]
+    paths: Vec<String>,
 }
 
 impl Options {
     /// Computes final options from the inputs.
     // Consumes so that this method can't unintentionally be called multiple times.
-    fn compute(self) -> Result<(ImageRef, OutputDir, Authentication)> {
+    fn compute(self) -> Result<(ImageRef, OutputDir, Authentication, Platform, PathFilter)> {
+        let filter = PathFilter::new(&self.paths)?;
         self.output
             .map(Ok)
             .unwrap_or_else(OutputDir::new_temporary)
             .context("create temporary output dir")
             .inspect(|output| println!("{output}"))
             .map(|output| {
+                // Credentials are resolved per candidate registry at resolve time, so
+                // only the CLI-provided credentials are carried here.
                 let auth = Authentication::new(self.username, self.password);
-                (self.image, output, auth)
+                let platform = self.platform.unwrap_or_else(Platform::host);
+                (self.image, output, auth, platform, filter)
             })
     }
 }
@@ -60,6 +87,12 @@ static MAGNIFIER: Emoji<'_, '_> = Emoji("🔍 ", "");
 static TRUCK: Emoji<'_, '_> = Emoji("🚚 ", "");
 static PACKAGE: Emoji<'_, '_> = Emoji("📦️ ", "");
 
+/// The prefix marking an OCI overlay whiteout entry.
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// The special whiteout entry marking its containing directory as opaque.
+const WHITEOUT_OPAQUE: &str = ".wh..wh..opq";
+
 /// Extracts the contents of the image to disk.
 ///
 /// By default:
@@ -69,22 +102,48 @@ static PACKAGE: Emoji<'_, '_> = Emoji("📦️ ", "");
 // Update the docs for the subcommand in `main` if you change this.
 pub async fn main(opts: Options) -> Result<()> {
     let client = Client::new(ClientConfig::default());
-    let (image, _output, auth) = opts.compute()?;
-    let ociref = Reference::from(&image);
-    let ociauth = RegistryAuth::from(&auth);
+    let (image, output, cli_auth, platform, filter) = opts.compute()?;
 
-    eprint!(
-        "{MAGNIFIER}Resolving manifest for {}...",
-        style(image.to_string()).bold().dim()
-    );
-    let manifest = client
-        .pull_image_manifest(&ociref, &ociauth)
-        .await
-        .map(|(manifest, digest)| {
-            eprintln!(" resolved manifest: {}", style(digest).bold().dim(),);
-            manifest
-        })
-        .context("resolve image manifest")?;
+    // Try each configured mirror in order, falling back to the original location.
+    let mirrors = MirrorConfig::load().context("load registry mirrors")?;
+    let candidates = mirrors.candidates(&image);
+
+    let mut resolved = None;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let auth = cli_auth
+            .clone()
+            .for_registry(&candidate.registry)
+            .context("resolve registry credentials")?;
+
+        if candidate == &image {
+            eprint!(
+                "{MAGNIFIER}Resolving manifest for {} ({platform})...",
+                style(image.to_string()).bold().dim()
+            );
+        } else {
+            eprint!(
+                "{MAGNIFIER}Resolving manifest for {} via mirror {} ({platform})...",
+                style(image.to_string()).bold().dim(),
+                style(candidate.registry.clone()).bold().dim(),
+            );
+        }
+
+        match candidate.resolve(&client, &auth, &platform).await {
+            Ok((manifest, digest)) => {
+                eprintln!(" resolved manifest: {}", style(digest).bold().dim());
+                resolved = Some((candidate.clone(), manifest));
+                break;
+            }
+            Err(err) if index + 1 < candidates.len() => {
+                eprintln!(" failed, trying next mirror");
+                tracing::warn!(registry = %candidate.registry, error = %err, "mirror failed");
+            }
+            Err(err) => return Err(err).context("resolve image manifest"),
+        }
+    }
+
+    let (served, manifest) = resolved.expect("at least the original candidate is attempted");
+    let ociref = Reference::from(&served);
 
     let working = TempDir::new().await.context("create temporary directory")?;
     eprintln!(
@@ -93,29 +152,48 @@ pub async fn main(opts: Options) -> Result<()> {
     );
 
     let layers = manifest.layers;
-    let task_count = layers.len() * 2; // Download + apply each layer
+    let layer_count = layers.len();
+    let task_count = layer_count * 2; // Download + apply each layer
     eprintln!(
         "{TRUCK}Pulling {} {}...",
-        style(layers.len().to_string()).bold().dim(),
-        pluralize("layer", "", "s", layers.len())
+        style(layer_count.to_string()).bold().dim(),
+        pluralize("layer", "", "s", layer_count)
     );
 
     let mut tasks = JoinSet::new();
     let progress = MultiProgress::new();
-    for (layer, task) in layers.into_iter().zip(1..) {
+    for (index, layer) in layers.into_iter().enumerate() {
         let blob = client
             .pull_blob_stream(&ociref, &layer)
             .await
             .with_context(|| format!("pull layer: {}", layer.digest))?;
 
-        let bar = download_progress(task, task_count, blob.content_length);
+        let bar = download_progress(index + 1, task_count, blob.content_length);
         let bar = progress.add(bar);
 
-        tasks.spawn(download_layer(bar, working.dir_path().clone(), layer, blob));
+        tasks.spawn(download_layer(
+            bar,
+            working.dir_path().clone(),
+            index,
+            layer,
+            blob,
+        ));
     }
 
+    // Downloads run concurrently, but layers must be applied strictly in manifest
+    // order (lower to higher) for whiteouts and overwrites to resolve correctly.
+    let mut downloaded = Vec::with_capacity(layer_count);
     while let Some(task) = tasks.join_next().await {
-        let _downloaded = task.expect("join task").context("download blob")?;
+        downloaded.push(task.expect("join task").context("download blob")?);
+    }
+    downloaded.sort_by_key(|(index, ..)| *index);
+
+    for (index, layer, path) in downloaded {
+        let bar = apply_progress(layer_count + index + 1, task_count);
+        let bar = progress.add(bar);
+        apply_layer(bar, &output.path, &layer, &path, &filter)
+            .await
+            .with_context(|| format!("apply layer: {}", layer.digest))?;
     }
 
     Ok(())
@@ -124,25 +202,322 @@ pub async fn main(opts: Options) -> Result<()> {
 async fn download_layer(
     progress: ProgressBar,
     working: PathBuf,
+    index: usize,
     layer: OciDescriptor,
     blob: SizedStream,
-) -> Result<PathBuf> {
+) -> Result<(usize, OciDescriptor, PathBuf)> {
     let name = layer.digest.replace(':', "_");
     let path = working.join(name);
     let mut file = tokio::fs::File::create(&path)
         .await
         .with_context(|| format!("create file: {}", path.display()))?;
 
+    let expected = ContentDigest::from_str(&layer.digest).context("parse layer digest")?;
+
     let read = StreamReader::new(blob.stream);
     let read = BufReader::new(read);
-    tokio::io::copy(&mut progress.wrap_async_read(read), &mut file)
+    let read = progress.wrap_async_read(read);
+    // Digest the bytes as they stream past, before they hit disk — no second pass.
+    let mut read = Sha256Reader::new(read);
+    tokio::io::copy(&mut read, &mut file)
         .await
         .context("download blob")?;
 
     file.flush().await.context("flush downloaded blob")?;
     progress.finish_and_clear();
 
-    Ok(path)
+    let computed = read.finalize_hex();
+    if expected.is_supported() {
+        expected
+            .verify_hex(&computed)
+            .with_context(|| format!("verify layer: {}", layer.digest))?;
+    } else {
+        tracing::warn!(
+            digest = %layer.digest,
+            "unsupported digest algorithm; skipping integrity verification"
+        );
+    }
+
+    Ok((index, layer, path))
+}
+
+/// Applies a single downloaded layer tarball on top of `output`, squashing it
+/// into the flattened rootfs.
+///
+/// Regular entries overwrite any file inherited from lower layers. OCI overlay
+/// whiteout markers are honored rather than written: a `.wh.<name>` entry deletes
+/// `<name>` (and its subtree) inherited from lower layers, and a `.wh..wh..opq`
+/// entry makes its containing directory opaque by removing every lower-layer entry
+/// in it before the current and higher layers are applied.
+async fn apply_layer(
+    progress: ProgressBar,
+    output: &Path,
+    layer: &OciDescriptor,
+    tar_path: &Path,
+    filter: &PathFilter,
+) -> Result<()> {
+    let file = tokio::fs::File::open(tar_path)
+        .await
+        .with_context(|| format!("open layer: {}", tar_path.display()))?;
+
+    // Decompress on the fly, keyed off the layer's media type, so whole layers are
+    // never buffered in memory between the download and the tar reader.
+    let compression = Compression::for_media_type(&layer.media_type)
+        .with_context(|| format!("classify layer: {}", layer.digest))?;
+    let read: Box<dyn AsyncRead + Unpin + Send> = compression.decode(BufReader::new(file));
+
+    // Paths written by *this* layer, so opaque whiteouts only remove content
+    // inherited from lower layers regardless of where the marker falls in tar order.
+    let mut written: HashSet<PathBuf> = HashSet::new();
+
+    let mut archive = Archive::new(read);
+    let mut entries = archive.entries().context("read layer entries")?;
+    while let Some(entry) = entries.next().await {
+        let mut entry = entry.context("read layer entry")?;
+        let path = entry.path().context("read entry path")?.into_owned();
+
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if name == WHITEOUT_OPAQUE {
+            // The marker's parent directory is made opaque.
+            let parent = path.parent().unwrap_or_else(|| Path::new(""));
+            let dir = resolve_within(output, parent).context("resolve opaque directory")?;
+            clear_dir(&dir, &written)
+                .await
+                .context("apply opaque whiteout")?;
+        } else if let Some(target) = name.strip_prefix(WHITEOUT_PREFIX) {
+            // Delete the named sibling (and its subtree) inherited from lower layers.
+            let parent = path.parent().unwrap_or_else(|| Path::new(""));
+            let victim =
+                resolve_within(output, &parent.join(target)).context("resolve whiteout target")?;
+            remove_path(&victim).await.context("apply whiteout")?;
+        } else {
+            // Guard against path traversal before handing the entry to the unpacker.
+            let resolved = resolve_within(output, &path).context("resolve entry path")?;
+
+            // Whiteouts above are always honored; regular entries are subject to the
+            // path filter so `--path` grabs a subset without materializing the rest.
+            if filter.matches(&path) {
+                // A `--path` filter can exclude a hardlink's target, which would make
+                // `unpack_in` fail on the missing target; skip such dangling hardlinks
+                // rather than aborting the whole extraction. This only applies when a
+                // filter is active — full extraction must still fail loudly on a
+                // genuinely missing hardlink target.
+                let dangling_hardlink = if filter.is_active()
+                    && entry.header().entry_type().is_hard_link()
+                {
+                    match entry.link_name().context("read hardlink target")? {
+                        Some(link) => {
+                            let target =
+                                resolve_within(output, &link).context("resolve hardlink target")?;
+                            !tokio::fs::try_exists(&target)
+                                .await
+                                .with_context(|| format!("stat hardlink target: {}", target.display()))?
+                        }
+                        None => false,
+                    }
+                } else {
+                    false
+                };
+
+                if dangling_hardlink {
+                    tracing::warn!(
+                        path = %path.display(),
+                        "skipping hardlink whose target was excluded by --path"
+                    );
+                } else {
+                    entry
+                        .unpack_in(output)
+                        .await
+                        .with_context(|| format!("unpack entry: {}", path.display()))?;
+                    written.insert(resolved);
+                }
+            }
+        }
+
+        progress.inc(1);
+    }
+
+    progress.finish_and_clear();
+    Ok(())
+}
+
+/// A set of path globs constraining which squashed entries are written to disk.
+/// An empty filter matches everything, preserving full-extraction behavior.
+struct PathFilter {
+    patterns: Vec<glob::Pattern>,
+}
+
+impl PathFilter {
+    /// Compile the raw `--path` globs, erroring on any invalid pattern.
+    fn new(globs: &[String]) -> Result<Self> {
+        let patterns = globs
+            .iter()
+            .map(|glob| glob::Pattern::new(glob).with_context(|| format!("invalid path glob: {glob}")))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Whether any `--path` globs were provided (i.e. extraction is being filtered).
+    fn is_active(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Whether an entry at `path` (relative to the rootfs) should be written.
+    fn matches(&self, path: &Path) -> bool {
+        // `*` does not cross `/`, while `**` does — so `usr/bin/*` is one level and
+        // `**/*.so` is recursive, matching how other OCI tooling treats path globs.
+        const OPTIONS: glob::MatchOptions = glob::MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+
+        if self.patterns.is_empty() {
+            return true;
+        }
+
+        // Tooling-produced layers prefix entries with `./` (e.g. `./usr/bin/ls`);
+        // drop those so globs match the same squashed paths `resolve_within` writes.
+        let normalized = path
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect::<PathBuf>();
+
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches_path_with(&normalized, OPTIONS))
+    }
+}
+
+/// The compression scheme of a layer blob, selected from its media type.
+enum Compression {
+    /// gzip-compressed tar (`...tar+gzip` / Docker `...tar.gzip`).
+    Gzip,
+
+    /// zstd-compressed tar (`...tar+zstd`).
+    Zstd,
+
+    /// An uncompressed tar (`...tar`).
+    None,
+}
+
+impl Compression {
+    /// Classify a layer by its `OciDescriptor::media_type`, erroring on anything
+    /// unrecognized rather than feeding garbage to the tar reader.
+    fn for_media_type(media_type: &str) -> Result<Self> {
+        match media_type {
+            "application/vnd.oci.image.layer.v1.tar+gzip"
+            | "application/vnd.docker.image.rootfs.diff.tar.gzip" => Ok(Self::Gzip),
+            "application/vnd.oci.image.layer.v1.tar+zstd" => Ok(Self::Zstd),
+            "application/vnd.oci.image.layer.v1.tar"
+            | "application/vnd.docker.image.rootfs.diff.tar" => Ok(Self::None),
+            other => bail!("unsupported layer media type: {other}"),
+        }
+    }
+
+    /// Wrap a buffered reader in the matching streaming decoder.
+    fn decode<R: tokio::io::AsyncBufRead + Unpin + Send + 'static>(
+        self,
+        read: R,
+    ) -> Box<dyn AsyncRead + Unpin + Send> {
+        match self {
+            Self::Gzip => Box::new(GzipDecoder::new(read)),
+            Self::Zstd => Box::new(ZstdDecoder::new(read)),
+            Self::None => Box::new(read),
+        }
+    }
+}
+
+/// Resolves `path` (a layer-relative entry path) against `base`, rejecting any
+/// path that escapes `base` once normalized. This guards against `../` traversal
+/// and absolute paths embedded in hostile layers.
+fn resolve_within(base: &Path, path: &Path) -> Result<PathBuf> {
+    let mut resolved = base.to_path_buf();
+    for component in path.components() {
+        match component {
+            Component::Normal(segment) => resolved.push(segment),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                ensure!(
+                    resolved.pop() && resolved.starts_with(base),
+                    "entry path escapes output directory: {}",
+                    path.display()
+                );
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                bail!("entry path is absolute: {}", path.display())
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Removes a file, symlink, or directory subtree, ignoring a missing target.
+async fn remove_path(path: &Path) -> Result<()> {
+    let metadata = match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err).with_context(|| format!("stat: {}", path.display())),
+    };
+
+    if metadata.is_dir() {
+        tokio::fs::remove_dir_all(path)
+            .await
+            .with_context(|| format!("remove directory: {}", path.display()))
+    } else {
+        tokio::fs::remove_file(path)
+            .await
+            .with_context(|| format!("remove file: {}", path.display()))
+    }
+}
+
+/// Removes every entry inherited from lower layers in `dir`, leaving the directory
+/// itself in place and preserving paths written by the current layer (`written`).
+///
+/// A preserved entry that is itself a directory is recursed into rather than kept
+/// whole, so lower-layer files nested inside a directory that also received a
+/// current-layer write are still removed. This makes the opaque marker's position
+/// in tar order irrelevant. A missing directory is treated as already empty.
+fn clear_dir<'a>(
+    dir: &'a Path,
+    written: &'a HashSet<PathBuf>,
+) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+    Box::pin(async move {
+        let mut reader = match tokio::fs::read_dir(dir).await {
+            Ok(reader) => reader,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read directory: {}", dir.display()))
+            }
+        };
+
+        while let Some(entry) = reader
+            .next_entry()
+            .await
+            .with_context(|| format!("read directory entry: {}", dir.display()))?
+        {
+            let child = entry.path();
+            if written.iter().any(|path| path.starts_with(&child)) {
+                // Something under `child` was written this layer. If `child` is a
+                // directory it may still hold lower-layer leaves, so recurse; if it
+                // is the written file itself, leave it be.
+                let file_type = entry
+                    .file_type()
+                    .await
+                    .with_context(|| format!("stat directory entry: {}", child.display()))?;
+                if file_type.is_dir() {
+                    clear_dir(&child, written).await?;
+                }
+            } else {
+                remove_path(&child).await?;
+            }
+        }
+
+        Ok(())
+    })
 }
 
 fn pluralize(base: &str, singular: &str, plural: &str, count: usize) -> String {
@@ -168,3 +543,128 @@ fn download_progress(task: usize, task_count: usize, bytes: Option<u64>) -> Prog
     bar.set_prefix(format!("[{task}/{task_count}]"));
     bar
 }
+
+fn apply_progress(task: usize, task_count: usize) -> ProgressBar {
+    static APPLY_STYLE: LazyLock<ProgressStyle> = LazyLock::new(|| {
+        ProgressStyle::with_template("{prefix:.dim} {spinner:.mint} applying layer {pos} entries")
+            .expect("parse progress bar template")
+    });
+
+    let bar = ProgressBar::new_spinner().with_style(APPLY_STYLE.clone());
+    bar.set_prefix(format!("[{task}/{task_count}]"));
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_filter_empty_matches_everything() {
+        let filter = PathFilter::new(&[]).expect("compile");
+        assert!(!filter.is_active());
+        assert!(filter.matches(Path::new("usr/bin/ls")));
+        assert!(filter.matches(Path::new("./etc/hosts")));
+    }
+
+    #[test]
+    fn path_filter_normalizes_leading_dot() {
+        let filter = PathFilter::new(&["usr/bin/*".to_string()]).expect("compile");
+        assert!(filter.is_active());
+        // Tooling-produced layers carry a leading `./`; both forms must match.
+        assert!(filter.matches(Path::new("./usr/bin/ls")));
+        assert!(filter.matches(Path::new("usr/bin/ls")));
+        // `*` does not cross `/`.
+        assert!(!filter.matches(Path::new("usr/bin/sub/ls")));
+        assert!(!filter.matches(Path::new("usr/lib/libc.so")));
+    }
+
+    #[test]
+    fn path_filter_double_star_recurses() {
+        let filter = PathFilter::new(&["**/*.so".to_string()]).expect("compile");
+        assert!(filter.matches(Path::new("lib/x86_64/libc.so")));
+        assert!(filter.matches(Path::new("./usr/lib/libm.so")));
+        assert!(!filter.matches(Path::new("bin/ls")));
+    }
+
+    #[test]
+    fn resolve_within_guards_traversal() {
+        let base = Path::new("/out");
+        assert_eq!(
+            resolve_within(base, Path::new("usr/bin/ls")).expect("ok"),
+            Path::new("/out/usr/bin/ls")
+        );
+        assert_eq!(
+            resolve_within(base, Path::new("./usr/bin")).expect("ok"),
+            Path::new("/out/usr/bin")
+        );
+        assert!(resolve_within(base, Path::new("../etc/passwd")).is_err());
+        assert!(resolve_within(base, Path::new("/etc/passwd")).is_err());
+        assert!(resolve_within(base, Path::new("a/../../b")).is_err());
+    }
+
+    #[test]
+    fn compression_by_media_type() {
+        assert!(matches!(
+            Compression::for_media_type("application/vnd.oci.image.layer.v1.tar+gzip")
+                .expect("gzip"),
+            Compression::Gzip
+        ));
+        assert!(matches!(
+            Compression::for_media_type("application/vnd.docker.image.rootfs.diff.tar.gzip")
+                .expect("docker gzip"),
+            Compression::Gzip
+        ));
+        assert!(matches!(
+            Compression::for_media_type("application/vnd.oci.image.layer.v1.tar+zstd")
+                .expect("zstd"),
+            Compression::Zstd
+        ));
+        assert!(matches!(
+            Compression::for_media_type("application/vnd.oci.image.layer.v1.tar").expect("plain"),
+            Compression::None
+        ));
+        assert!(Compression::for_media_type("application/json").is_err());
+    }
+
+    #[tokio::test]
+    async fn clear_dir_removes_nested_lower_layer_files() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = tmp.path();
+
+        // A lower layer populated `etc/conf.d/old.conf` and an unrelated `etc/other`.
+        let confd = root.join("etc/conf.d");
+        tokio::fs::create_dir_all(&confd).await.expect("mkdir conf.d");
+        tokio::fs::write(confd.join("old.conf"), b"old")
+            .await
+            .expect("write old.conf");
+        let other = root.join("etc/other");
+        tokio::fs::create_dir_all(&other).await.expect("mkdir other");
+        tokio::fs::write(other.join("lower.txt"), b"lower")
+            .await
+            .expect("write lower.txt");
+
+        // The current layer wrote `etc/conf.d/new.conf` before the opaque marker.
+        tokio::fs::write(confd.join("new.conf"), b"new")
+            .await
+            .expect("write new.conf");
+        let mut written = HashSet::new();
+        written.insert(confd.join("new.conf"));
+
+        clear_dir(&root.join("etc"), &written).await.expect("clear");
+
+        // Opaque semantics: every lower-layer entry gone, current-layer write kept.
+        assert!(
+            !confd.join("old.conf").exists(),
+            "nested lower-layer file must be removed"
+        );
+        assert!(
+            confd.join("new.conf").exists(),
+            "current-layer write must be preserved"
+        );
+        assert!(
+            !other.exists(),
+            "purely lower-layer subtree must be removed whole"
+        );
+    }
+}
@@ -10,12 +10,28 @@ use color_eyre::{
     Report, Result,
 };
 use derive_more::derive::{Debug, Display};
-use oci_client::{manifest::OciImageManifest, Client, Reference};
-use std::{path::PathBuf, str::FromStr};
+use oci_client::{
+    manifest::{OciDescriptor, OciImageManifest, OciManifest, Platform as OciPlatform},
+    Client, Reference,
+};
+use base64::Engine as _;
+use pin_project_lite::pin_project;
+use serde::Deserialize;
+use sha2::{Digest as _, Sha256};
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::PathBuf,
+    pin::Pin,
+    process::{Command, Stdio},
+    str::FromStr,
+    task::{Context as TaskContext, Poll},
+};
+use tokio::io::{AsyncRead, ReadBuf};
 
 /// Import this with a glob to use all the major types and traits in the library.
 pub mod prelude {
-    pub use crate::{ImageRef, ImageRefVersion, OutputDir};
+    pub use crate::{ContentDigest, ImageRef, ImageRefVersion, MirrorConfig, OutputDir, Platform};
 }
 
 /// A parsed container image reference.
@@ -34,28 +50,68 @@ pub struct ImageRef {
 }
 
 impl ImageRef {
-    /// Resolve the image reference with the backend.
+    /// Resolve the image reference with the backend, returning the image manifest
+    /// for `platform` along with its digest.
+    ///
+    /// Multi-platform images (OCI image indexes / Docker manifest lists) are
+    /// resolved transparently: the child manifest matching `platform` is selected
+    /// and pulled, so the returned manifest is always a concrete, per-platform one.
     pub async fn resolve(
         &self,
         client: &Client,
         auth: &Authentication,
+        platform: &Platform,
     ) -> Result<(OciImageManifest, String)> {
-        let (registry, repository, version) = (
-            self.registry.clone(),
-            self.repository.clone(),
-            self.version.clone(),
-        );
-
+        let reference = Reference::from(self);
         let auth = auth.into();
-        let reference = match version {
-            ImageRefVersion::Tag(tag) => Reference::with_tag(registry, repository, tag),
-            ImageRefVersion::Digest(digest) => Reference::with_digest(registry, repository, digest),
-        };
 
-        client
-            .pull_image_manifest(&reference, &auth)
+        let (manifest, digest) = client
+            .pull_manifest(&reference, &auth)
             .await
-            .context("pull image manifest")
+            .context("pull manifest")?;
+
+        // If the caller pinned a digest, whatever we got back must match it before
+        // we trust it enough to descend into an index.
+        if let ImageRefVersion::Digest(requested) = &self.version {
+            let requested = requested.parse::<ContentDigest>().context("parse requested digest")?;
+            let resolved = digest.parse::<ContentDigest>().context("parse resolved digest")?;
+            requested
+                .verify_hex(&resolved.hex)
+                .context("verify manifest digest")?;
+        }
+
+        match manifest {
+            OciManifest::Image(image) => Ok((image, digest)),
+            OciManifest::ImageIndex(index) => {
+                let descriptor = platform.select(&index.manifests)?;
+                let child = Reference::with_digest(
+                    self.registry.clone(),
+                    self.repository.clone(),
+                    descriptor.digest.clone(),
+                );
+                let (image, child_digest) = client
+                    .pull_image_manifest(&child, &auth)
+                    .await
+                    .context("pull platform manifest")?;
+
+                // The child we pulled by digest must hash back to the digest the
+                // index advertised for it.
+                let expected = descriptor
+                    .digest
+                    .parse::<ContentDigest>()
+                    .context("parse child descriptor digest")?;
+                let resolved = child_digest
+                    .parse::<ContentDigest>()
+                    .context("parse child manifest digest")?;
+                if expected.is_supported() {
+                    expected
+                        .verify_hex(&resolved.hex)
+                        .context("verify child manifest digest")?;
+                }
+
+                Ok((image, child_digest))
+            }
+        }
     }
 }
 
@@ -128,6 +184,341 @@ pub enum ImageRefVersion {
     Digest(String),
 }
 
+/// Registry mirror/remap configuration, analogous to `containers-registries.conf`.
+///
+/// Each rule maps a source `registry/repository` prefix to one or more mirror
+/// locations, letting an administrator pull, say, `docker.io/library/...`
+/// transparently from an internal registry without rewriting image specs.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MirrorConfig {
+    #[serde(default, rename = "registry")]
+    registries: Vec<RegistryRule>,
+}
+
+/// A single prefix-to-mirrors remapping rule.
+#[derive(Clone, Debug, Deserialize)]
+struct RegistryRule {
+    /// The source `registry` or `registry/repository` prefix this rule matches.
+    prefix: String,
+
+    /// The mirrors to try, in order, before the original location.
+    #[serde(default, rename = "mirror")]
+    mirrors: Vec<Mirror>,
+}
+
+/// A single mirror location for a [`RegistryRule`].
+#[derive(Clone, Debug, Deserialize)]
+struct Mirror {
+    /// The `registry` or `registry/repository` prefix to rewrite matching refs to.
+    location: String,
+
+    /// Whether the mirror is only reachable over insecure transport.
+    #[serde(default)]
+    insecure: bool,
+}
+
+impl MirrorConfig {
+    /// Load the config from `$BOXPOP_REGISTRIES_CONF`, else
+    /// `$XDG_CONFIG_HOME/boxpop/registries.toml` (or `~/.config/...`). A missing
+    /// file yields an empty config that remaps nothing.
+    pub fn load() -> Result<Self> {
+        let Some(path) = mirror_config_path() else {
+            return Ok(Self::default());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("parse registry mirrors: {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("read registry mirrors: {}", path.display())),
+        }
+    }
+
+    /// The ordered list of image references to try for `image`: the mirrors of the
+    /// first matching rule, followed by the original location as a fallback.
+    ///
+    /// Insecure mirrors are skipped (their transport is unsupported), and any
+    /// mirror whose location cannot be parsed back into a registry/repository is
+    /// dropped; in both cases resolution falls back to the original.
+    pub fn candidates(&self, image: &ImageRef) -> Vec<ImageRef> {
+        let full = format!("{}/{}", image.registry, image.repository);
+        let mut candidates = Vec::new();
+
+        if let Some(rule) = self
+            .registries
+            .iter()
+            .find(|rule| prefix_matches(&rule.prefix, &full))
+        {
+            for mirror in &rule.mirrors {
+                if mirror.insecure {
+                    tracing::warn!(
+                        location = %mirror.location,
+                        "skipping insecure mirror; insecure transport is unsupported"
+                    );
+                    continue;
+                }
+                match rewrite(image, &rule.prefix, &mirror.location) {
+                    Some(candidate) => candidates.push(candidate),
+                    None => tracing::warn!(
+                        location = %mirror.location,
+                        "skipping mirror; location is not a valid registry/repository"
+                    ),
+                }
+            }
+        }
+
+        candidates.push(image.clone());
+        candidates
+    }
+}
+
+/// Whether a rule `prefix` matches `full` (`registry/repository`) at a path boundary.
+fn prefix_matches(prefix: &str, full: &str) -> bool {
+    full == prefix || full.starts_with(&format!("{prefix}/"))
+}
+
+/// Rewrite `image` by replacing the matched `prefix` of its `registry/repository`
+/// with `location`, re-splitting the result back into registry and repository.
+fn rewrite(image: &ImageRef, prefix: &str, location: &str) -> Option<ImageRef> {
+    let full = format!("{}/{}", image.registry, image.repository);
+    let rest = full.strip_prefix(prefix)?;
+    let rewritten = format!("{location}{rest}");
+    let (registry, repository) = rewritten.split_once('/')?;
+    Some(ImageRef {
+        registry: registry.to_string(),
+        repository: repository.to_string(),
+        version: image.version.clone(),
+    })
+}
+
+/// The path to the registry mirror config, if one can be located.
+fn mirror_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("BOXPOP_REGISTRIES_CONF") {
+        return Some(PathBuf::from(path));
+    }
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("boxpop").join("registries.toml"))
+}
+
+/// A platform selector for multi-platform images, in `os/arch[/variant]` form.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[debug("{}", self)]
+pub struct Platform {
+    /// The operating system, using OCI naming (e.g. `linux`, `darwin`, `windows`).
+    pub os: String,
+
+    /// The CPU architecture, using OCI naming (e.g. `amd64`, `arm64`, `arm`).
+    pub arch: String,
+
+    /// The architecture variant, if any (e.g. `v7` for 32-bit ARM).
+    pub variant: Option<String>,
+}
+
+impl Platform {
+    /// The platform this program is currently running on, mapped to OCI naming.
+    pub fn host() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            other => other,
+        };
+        let (arch, variant) = match std::env::consts::ARCH {
+            "x86_64" => ("amd64", None),
+            "aarch64" => ("arm64", None),
+            "arm" => ("arm", Some("v7")),
+            other => (other, None),
+        };
+        Self {
+            os: os.to_string(),
+            arch: arch.to_string(),
+            variant: variant.map(String::from),
+        }
+    }
+
+    /// Select the index entry matching this platform, erroring with the list of
+    /// available platforms when nothing matches.
+    pub fn select<'a>(&self, manifests: &'a [OciDescriptor]) -> Result<&'a OciDescriptor> {
+        if let Some(descriptor) = manifests
+            .iter()
+            .find(|descriptor| descriptor.platform.as_ref().is_some_and(|p| self.matches(p)))
+        {
+            return Ok(descriptor);
+        }
+
+        let available = manifests
+            .iter()
+            .filter_map(|descriptor| descriptor.platform.as_ref())
+            .map(format_oci_platform)
+            .collect::<Vec<_>>()
+            .join(", ");
+        bail!("no manifest matches platform {self}; available platforms: [{available}]")
+    }
+
+    /// Whether this selector matches an index entry's platform. A variant is only
+    /// required to match when one was requested.
+    fn matches(&self, platform: &OciPlatform) -> bool {
+        self.os == platform.os
+            && self.arch == platform.architecture
+            && match &self.variant {
+                Some(variant) => platform.variant.as_deref() == Some(variant.as_str()),
+                None => true,
+            }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, '/');
+        let os = parts.next().filter(|p| !p.is_empty());
+        let arch = parts.next().filter(|p| !p.is_empty());
+        match (os, arch) {
+            (Some(os), Some(arch)) => Ok(Self {
+                os: os.to_string(),
+                arch: arch.to_string(),
+                variant: parts.next().map(String::from),
+            }),
+            _ => bail!("platform must be `os/arch[/variant]`"),
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.variant {
+            Some(variant) => write!(f, "{}/{}/{variant}", self.os, self.arch),
+            None => write!(f, "{}/{}", self.os, self.arch),
+        }
+    }
+}
+
+fn format_oci_platform(platform: &OciPlatform) -> String {
+    match &platform.variant {
+        Some(variant) => format!("{}/{}/{variant}", platform.os, platform.architecture),
+        None => format!("{}/{}", platform.os, platform.architecture),
+    }
+}
+
+/// A content digest of the form `algorithm:hex`, as used by `OciDescriptor::digest`.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display("{algorithm}:{hex}")]
+pub struct ContentDigest {
+    /// The hash algorithm used to compute the digest.
+    pub algorithm: DigestAlgorithm,
+
+    /// The lowercase hex-encoded hash value.
+    pub hex: String,
+}
+
+impl ContentDigest {
+    /// Whether this program is able to verify content against this digest.
+    /// Unsupported algorithms are parsed but skipped so future descriptors degrade gracefully.
+    pub fn is_supported(&self) -> bool {
+        matches!(self.algorithm, DigestAlgorithm::Sha256)
+    }
+
+    /// Verify a freshly computed lowercase hex digest against this one, bailing on mismatch.
+    pub fn verify_hex(&self, computed: &str) -> Result<()> {
+        ensure!(
+            self.hex.eq_ignore_ascii_case(computed),
+            "digest mismatch: expected {self}, computed {}:{computed}",
+            self.algorithm
+        );
+        Ok(())
+    }
+}
+
+impl FromStr for ContentDigest {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, hex) = s.split_once(':').context("digest must be `algorithm:hex`")?;
+        ensure!(!hex.is_empty(), "digest hex must be provided");
+        Ok(Self {
+            algorithm: algorithm.parse()?,
+            hex: hex.to_string(),
+        })
+    }
+}
+
+/// The hash algorithm component of a [`ContentDigest`].
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+pub enum DigestAlgorithm {
+    /// SHA-256; the algorithm used by essentially all current OCI content.
+    #[display("sha256")]
+    Sha256,
+
+    /// SHA-512; parsed but not yet verified.
+    #[display("sha512")]
+    Sha512,
+
+    /// An algorithm this program does not recognize.
+    #[display("{_0}")]
+    Other(String),
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ensure!(!s.is_empty(), "digest algorithm must be provided");
+        Ok(match s {
+            "sha256" => Self::Sha256,
+            "sha512" => Self::Sha512,
+            other => Self::Other(other.to_string()),
+        })
+    }
+}
+
+pin_project! {
+    /// An [`AsyncRead`] adapter that incrementally feeds every byte read through it
+    /// into a SHA-256 hasher, enabling streaming integrity verification of a blob
+    /// without a second pass over the data.
+    pub struct Sha256Reader<R> {
+        #[pin]
+        inner: R,
+        hasher: Sha256,
+    }
+}
+
+impl<R> Sha256Reader<R> {
+    /// Wrap a reader so that all bytes read through it are digested.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Consume the reader, returning the lowercase hex digest of every byte read.
+    pub fn finalize_hex(self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for Sha256Reader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.project();
+        let before = buf.filled().len();
+        let poll = this.inner.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = &poll {
+            this.hasher.update(&buf.filled()[before..]);
+        }
+        poll
+    }
+}
+
 /// The output directory to which extracted container content is written.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Display, Debug)]
 #[debug("{}", self)]
@@ -200,6 +591,199 @@ impl Authentication {
             (Some(username), None) => Self::Basic(username, String::new()),
         }
     }
+
+    /// Resolve the credentials to use for `registry`.
+    ///
+    /// Resolution order mirrors the other OCI tooling: explicit CLI flags (i.e. a
+    /// non-[`Authentication::None`] `self`) win; otherwise the Docker config
+    /// (`$DOCKER_CONFIG` or `~/.docker/config.json`) is consulted for a matching
+    /// `auths`/`credHelpers`/`credsStore` entry; failing that, authentication is
+    /// anonymous.
+    pub fn for_registry(self, registry: &str) -> Result<Self> {
+        if !matches!(self, Self::None) {
+            return Ok(self);
+        }
+
+        match DockerConfig::load().context("load docker config")? {
+            Some(config) => config.credentials(registry),
+            None => Ok(Self::None),
+        }
+    }
+}
+
+/// A subset of `~/.docker/config.json` sufficient to discover registry credentials.
+#[derive(Debug, Default, Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuthEntry>,
+
+    #[serde(default, rename = "credHelpers")]
+    cred_helpers: HashMap<String, String>,
+
+    #[serde(default, rename = "credsStore")]
+    creds_store: Option<String>,
+}
+
+/// A single `auths` entry; either a base64 `auth` blob or split username/password.
+#[derive(Debug, Default, Deserialize)]
+struct DockerAuthEntry {
+    #[serde(default)]
+    auth: Option<String>,
+
+    #[serde(default)]
+    username: Option<String>,
+
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// The response emitted by a `docker-credential-<helper> get` invocation.
+#[derive(Debug, Deserialize)]
+struct CredentialHelperResponse {
+    #[serde(rename = "Username")]
+    username: String,
+
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+impl DockerConfig {
+    /// Load and parse the Docker config, returning `None` when no config file exists.
+    fn load() -> Result<Option<Self>> {
+        let path = match std::env::var_os("DOCKER_CONFIG") {
+            Some(dir) => PathBuf::from(dir).join("config.json"),
+            None => match std::env::var_os("HOME") {
+                Some(home) => PathBuf::from(home).join(".docker").join("config.json"),
+                None => return Ok(None),
+            },
+        };
+
+        match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .with_context(|| format!("parse docker config: {}", path.display()))
+                .map(Some),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("read docker config: {}", path.display())),
+        }
+    }
+
+    /// Resolve credentials for `registry`, preferring credential helpers over the
+    /// static `auths` map, and falling back to anonymous.
+    fn credentials(&self, registry: &str) -> Result<Authentication> {
+        if let Some(helper) = self
+            .cred_helpers
+            .iter()
+            .find(|(host, _)| registry_matches(host, registry))
+            .map(|(_, helper)| helper)
+        {
+            return run_credential_helper(helper, registry);
+        }
+
+        // A `credsStore` entry takes precedence over the static `auths` map: when one
+        // is set, `docker login` leaves only an empty placeholder in `auths`, so the
+        // real credentials live behind the helper. Match Docker's precedence of
+        // credHelpers → credsStore → auths.
+        if let Some(helper) = &self.creds_store {
+            return run_credential_helper(helper, registry);
+        }
+
+        if let Some(credentials) = self
+            .auths
+            .iter()
+            .find(|(host, _)| registry_matches(host, registry))
+            .map(|(_, entry)| entry.credentials())
+            .transpose()?
+            .flatten()
+        {
+            return Ok(credentials);
+        }
+
+        Ok(Authentication::None)
+    }
+}
+
+impl DockerAuthEntry {
+    /// Decode this entry into [`Authentication`], honoring either the base64 `auth`
+    /// blob or an explicit username/password pair.
+    ///
+    /// Returns `None` for a credential-less placeholder (as `docker login` writes
+    /// when a `credsStore`/`credHelpers` backs the registry) so resolution can fall
+    /// through rather than treating it as anonymous or erroring.
+    fn credentials(&self) -> Result<Option<Authentication>> {
+        if let Some(auth) = self.auth.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(auth)
+                .context("decode base64 auth entry")?;
+            let decoded = String::from_utf8(decoded).context("auth entry is not valid utf-8")?;
+            let (username, password) = decoded
+                .split_once(':')
+                .context("auth entry is not `username:password`")?;
+            return Ok(Some(Authentication::Basic(
+                username.to_string(),
+                password.to_string(),
+            )));
+        }
+
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => Ok(Some(Authentication::Basic(
+                username.clone(),
+                password.clone(),
+            ))),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Invoke `docker-credential-<helper> get` with `registry` on stdin and parse the
+/// `{"Username", "Secret"}` JSON response into [`Authentication`].
+fn run_credential_helper(helper: &str, registry: &str) -> Result<Authentication> {
+    let program = format!("docker-credential-{helper}");
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("spawn credential helper: {program}"))?;
+
+    child
+        .stdin
+        .take()
+        .context("credential helper stdin unavailable")?
+        .write_all(registry.as_bytes())
+        .context("write registry to credential helper")?;
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("run credential helper: {program}"))?;
+    ensure!(
+        output.status.success(),
+        "credential helper {program} failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    let response: CredentialHelperResponse =
+        serde_json::from_slice(&output.stdout).context("parse credential helper response")?;
+    Ok(Authentication::Basic(response.username, response.secret))
+}
+
+/// Whether a Docker config host key refers to `registry`, accounting for scheme
+/// and path decoration as well as Docker Hub's several canonical hostnames.
+fn registry_matches(key: &str, registry: &str) -> bool {
+    let host = key
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or(host);
+
+    host == registry || (is_docker_hub(host) && is_docker_hub(registry))
+}
+
+/// Whether `host` is one of Docker Hub's interchangeable canonical hostnames.
+fn is_docker_hub(host: &str) -> bool {
+    matches!(
+        host,
+        "docker.io" | "index.docker.io" | "registry-1.docker.io"
+    )
 }
 
 impl From<&Authentication> for oci_client::secrets::RegistryAuth {
@@ -215,3 +799,120 @@ fn make_absolute(path: impl Into<PathBuf>) -> Result<PathBuf> {
     let path = path.into();
     std::fs::canonicalize(path).context("canonicalize path using working directory")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_content_digest() {
+        let digest = "sha256:abc123".parse::<ContentDigest>().expect("parse");
+        assert_eq!(digest.algorithm, DigestAlgorithm::Sha256);
+        assert_eq!(digest.hex, "abc123");
+        assert!(digest.is_supported());
+
+        let digest = "sha512:deadbeef".parse::<ContentDigest>().expect("parse");
+        assert_eq!(digest.algorithm, DigestAlgorithm::Sha512);
+        assert!(!digest.is_supported());
+
+        assert_eq!(
+            "blake3:00".parse::<ContentDigest>().expect("parse").algorithm,
+            DigestAlgorithm::Other("blake3".to_string())
+        );
+
+        assert!("sha256:".parse::<ContentDigest>().is_err());
+        assert!("missing-colon".parse::<ContentDigest>().is_err());
+    }
+
+    #[test]
+    fn verifies_digest_case_insensitively() {
+        let digest = "sha256:ABCDEF".parse::<ContentDigest>().expect("parse");
+        assert!(digest.verify_hex("abcdef").is_ok());
+        assert!(digest.verify_hex("ffffff").is_err());
+    }
+
+    #[test]
+    fn parses_platform() {
+        let platform = "linux/amd64".parse::<Platform>().expect("parse");
+        assert_eq!(platform.os, "linux");
+        assert_eq!(platform.arch, "amd64");
+        assert_eq!(platform.variant, None);
+        assert_eq!(platform.to_string(), "linux/amd64");
+
+        let platform = "linux/arm/v7".parse::<Platform>().expect("parse");
+        assert_eq!(platform.variant.as_deref(), Some("v7"));
+        assert_eq!(platform.to_string(), "linux/arm/v7");
+
+        assert!("linux".parse::<Platform>().is_err());
+        assert!("/amd64".parse::<Platform>().is_err());
+        assert!("".parse::<Platform>().is_err());
+    }
+
+    #[test]
+    fn matches_registry_hosts() {
+        assert!(registry_matches("docker.io", "docker.io"));
+        assert!(registry_matches("https://index.docker.io/v1/", "docker.io"));
+        assert!(registry_matches("registry-1.docker.io", "index.docker.io"));
+        assert!(registry_matches("ghcr.io", "ghcr.io"));
+        assert!(!registry_matches("ghcr.io", "docker.io"));
+    }
+
+    #[test]
+    fn matches_mirror_prefixes() {
+        assert!(prefix_matches(
+            "docker.io/library",
+            "docker.io/library/alpine"
+        ));
+        assert!(prefix_matches("docker.io", "docker.io/library/alpine"));
+        assert!(prefix_matches("docker.io/library", "docker.io/library"));
+        assert!(!prefix_matches("docker.io/lib", "docker.io/library/alpine"));
+        assert!(!prefix_matches("ghcr.io", "docker.io/library/alpine"));
+    }
+
+    #[test]
+    fn rewrites_to_mirror() {
+        let image = "docker.io/library/alpine:3.19"
+            .parse::<ImageRef>()
+            .expect("parse");
+        let rewritten = rewrite(&image, "docker.io/library", "internal.example.com/mirror")
+            .expect("rewrite");
+        assert_eq!(rewritten.registry, "internal.example.com");
+        assert_eq!(rewritten.repository, "mirror/alpine");
+        assert_eq!(rewritten.version, image.version);
+
+        // A location without a repository component cannot be re-split.
+        assert!(rewrite(&image, "docker.io/library/alpine", "mirror.invalid").is_none());
+    }
+
+    #[test]
+    fn decodes_auth_entries() {
+        // base64 of "user:pass".
+        let entry = DockerAuthEntry {
+            auth: Some("dXNlcjpwYXNz".to_string()),
+            username: None,
+            password: None,
+        };
+        assert_eq!(
+            entry.credentials().expect("decode"),
+            Some(Authentication::Basic("user".to_string(), "pass".to_string()))
+        );
+
+        // An empty placeholder must fall through rather than erroring or going anonymous.
+        let entry = DockerAuthEntry {
+            auth: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(entry.credentials().expect("empty"), None);
+        assert_eq!(DockerAuthEntry::default().credentials().expect("default"), None);
+
+        let entry = DockerAuthEntry {
+            auth: None,
+            username: Some("u".to_string()),
+            password: Some("p".to_string()),
+        };
+        assert_eq!(
+            entry.credentials().expect("explicit"),
+            Some(Authentication::Basic("u".to_string(), "p".to_string()))
+        );
+    }
+}
@@ -28,6 +28,11 @@ pub struct Application {
     #[clap(global = true, long, requires = "username", env = "OCI_PASSWORD")]
     password: Option<String>,
 
+    /// Select a platform for multi-platform images, as `os/arch[/variant]`.
+    /// If not set, the platform this program is running on is used.
+    #[clap(global = true, long)]
+    platform: Option<Platform>,
+
     #[clap(subcommand)]
     command: Command,
 }